@@ -5,128 +5,569 @@ use std::str::Chars;
 static LEGAL_EXIT_CHARS: [char; 1] = ['}'];
 static RESERVED_CHARS: [char; 2] = ['\x27', '{'];
 
+/// A single position in the source text, tracked both as a byte offset
+/// (for slicing the original text) and as a 1-indexed line/column pair
+/// (for human-readable diagnostics).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pos {
+    pub byte: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Pos {
+    fn start() -> Self {
+        Self {
+            byte: 0,
+            line: 1,
+            col: 1,
+        }
+    }
+}
+
+/// The region of source text that produced a token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: Pos,
+    pub end: Pos,
+}
+
+/// A value (usually a [`Token`]) together with the span of source text
+/// it was read from.
+#[derive(Debug, PartialEq)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub span: Span,
+}
+
+/// The base a [`Token::Int`] literal was written in, so that consumers
+/// can tell `0xFF` apart from `255` if they care to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Radix {
+    Binary,
+    Octal,
+    Decimal,
+    Hexadecimal,
+}
+
+impl Radix {
+    fn radix(self) -> u32 {
+        match self {
+            Self::Binary => 2,
+            Self::Octal => 8,
+            Self::Decimal => 10,
+            Self::Hexadecimal => 16,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, PartialOrd)]
 pub enum Token {
     EOF,
     Function(String),
-    Int(i64),
+    Int(i64, Radix),
     Float(f64),
     Symbol(String),
     String(String),
     OpeningBrace,
     ClosingBrace,
+    /// A `# ...`, `// ...`, or `{- ... -}` comment, stripped by default
+    /// and only ever produced when the lexer is built with
+    /// [`Lexer::keep_comments`].
+    Comment(String),
+    /// Input that could not be turned into a real token. Only ever
+    /// produced by [`Lexer::tokenize_recovering`], which keeps going
+    /// past bad input instead of aborting. The text is the error's
+    /// offending token plus whatever was skipped up to the resync
+    /// point. For numbers, function names and block comments that is
+    /// the raw source lexeme; for strings and `'` symbols it is not:
+    /// the opening quote is left out, string escapes appear decoded,
+    /// and a bad escape contributes only the text from its backslash
+    /// on. The token's span always covers the whole bad lexeme.
+    Error(String),
 }
 
-pub enum LexerError {
+/// The broad category of a [`LexerError`], without the positional or
+/// textual context around it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LexerErrorKind {
     InvalidNumber,
     InvalidToken,
     InvalidSymbolName,
     UnterminatedString,
+    InvalidEscape,
+    UnterminatedComment,
 }
 
-impl fmt::Debug for LexerError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{{ file: {}, line: {} }}", file!(), line!())
-    }
-}
-
-impl fmt::Display for LexerError {
+impl fmt::Display for LexerErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::InvalidNumber => write!(f, "invalid number"),
             Self::InvalidToken => write!(f, "invalid token"),
             Self::InvalidSymbolName => write!(f, "invalid symbol name"),
             Self::UnterminatedString => write!(f, "unterminated string"),
+            Self::InvalidEscape => write!(f, "invalid escape sequence"),
+            Self::UnterminatedComment => write!(f, "unterminated comment"),
         }
     }
 }
 
-impl From<std::num::ParseIntError> for LexerError {
-    fn from(_: std::num::ParseIntError) -> Self {
-        Self::InvalidNumber
+/// A lexing failure, pinned to the file and position it occurred at so
+/// it can be reported back to the user instead of pointing at this
+/// module's own source location.
+pub struct LexerError {
+    pub file_name: Option<String>,
+    pub line: usize,
+    pub col: usize,
+    pub offending_token: Option<String>,
+    pub kind: LexerErrorKind,
+}
+
+impl fmt::Debug for LexerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{self}")
     }
 }
 
-impl From<std::num::ParseFloatError> for LexerError {
-    fn from(_: std::num::ParseFloatError) -> Self {
-        Self::InvalidNumber
+impl fmt::Display for LexerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.file_name {
+            Some(file_name) => write!(f, "{}:{}:{}: ", file_name, self.line, self.col)?,
+            None => write!(f, "{}:{}: ", self.line, self.col)?,
+        }
+
+        write!(f, "{}", self.kind)?;
+
+        if let Some(offending_token) = &self.offending_token {
+            write!(f, " near \"{offending_token}\"")?;
+        }
+
+        Ok(())
     }
 }
 
 impl std::error::Error for LexerError {}
 
-enum Either<L, R> {
-    Left(L),
-    Right(R),
+/// The value produced by scanning a number literal, before it is
+/// wrapped up into a [`Token::Int`] or [`Token::Float`].
+enum NumberLiteral {
+    Int(i64, Radix),
+    Float(f64),
+}
+
+/// Which part of a decimal number literal is currently being scanned,
+/// used to reject malformed phase transitions (a second `.`, a `.`
+/// after an exponent, and so on).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NumberPhase {
+    Integer,
+    Fraction,
+    Exponent,
+}
+
+/// A `Chars` iterator that keeps track of the position it is about to
+/// yield next, so callers can stamp spans on the tokens they build from
+/// it.
+struct PosChars<'s> {
+    chars: Peekable<Chars<'s>>,
+    pos: Pos,
+}
+
+impl<'s> PosChars<'s> {
+    fn new(text: &'s str) -> Self {
+        Self {
+            chars: text.chars().peekable(),
+            pos: Pos::start(),
+        }
+    }
+
+    fn peek(&mut self) -> Option<&char> {
+        self.chars.peek()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let ch = self.chars.next()?;
+
+        self.pos.byte += ch.len_utf8();
+        if ch == '\n' {
+            self.pos.line += 1;
+            self.pos.col = 1;
+        } else {
+            self.pos.col += 1;
+        }
+
+        Some(ch)
+    }
 }
 
 pub struct Lexer<'s> {
-    text: Peekable<Chars<'s>>,
+    text: PosChars<'s>,
+    file_name: Option<String>,
+    done: bool,
+    keep_comments: bool,
 }
 
 impl<'s> Lexer<'s> {
     pub fn new(text: &'s str) -> Self {
         Self {
-            text: text.chars().peekable(),
+            text: PosChars::new(text),
+            file_name: None,
+            done: false,
+            keep_comments: false,
         }
     }
 
-    fn make_number(&mut self, ch: char) -> Result<Either<i64, f64>, LexerError> {
-        let mut has_dot = ch == '.';
+    /// Attach a file name to this lexer so that any [`LexerError`] it
+    /// produces can point back at the file the bad input came from.
+    pub fn with_file_name(mut self, file_name: impl Into<String>) -> Self {
+        self.file_name = Some(file_name.into());
+        self
+    }
+
+    /// Emit comments as `Token::Comment` instead of silently stripping
+    /// them, so formatting/documentation tools can round-trip the
+    /// source.
+    pub fn keep_comments(mut self, keep: bool) -> Self {
+        self.keep_comments = keep;
+        self
+    }
+
+    fn error(&self, kind: LexerErrorKind, offending_token: Option<String>) -> LexerError {
+        self.error_at(self.text.pos, kind, offending_token)
+    }
+
+    fn error_at(&self, pos: Pos, kind: LexerErrorKind, offending_token: Option<String>) -> LexerError {
+        LexerError {
+            file_name: self.file_name.clone(),
+            line: pos.line,
+            col: pos.col,
+            offending_token,
+            kind,
+        }
+    }
+
+    /// Build an `InvalidNumber` error, extending the offending lexeme
+    /// with whatever remains of the bad run up to the next resync
+    /// point so the diagnostic names the whole token the user typed
+    /// (e.g. `12.3.4`, not just the `12.3` consumed so far).
+    fn invalid_number(&mut self, partial: String) -> LexerError {
+        let mut err = self.error(LexerErrorKind::InvalidNumber, Some(partial));
+
+        if let Some(token) = &mut err.offending_token {
+            token.push_str(&self.resync());
+        }
+
+        err
+    }
+
+    /// Like [`Lexer::invalid_number`], but for a radix literal: `partial`
+    /// is just the digits scanned so far, so the `0x`/`0o`/`0b` marker is
+    /// stitched back on before extending to the resync point, so the
+    /// diagnostic names the whole lexeme (e.g. `0xGG`) instead of just
+    /// the bad digit run.
+    fn invalid_radix_number(&mut self, prefix: &str, partial: String) -> LexerError {
+        self.invalid_number(format!("{prefix}{partial}"))
+    }
+
+    fn make_number(
+        &mut self,
+        ch: char,
+        start: Pos,
+    ) -> Result<Spanned<NumberLiteral>, LexerError> {
+        if ch == '0' {
+            let radix = match self.text.peek() {
+                Some('x') => Some(Radix::Hexadecimal),
+                Some('o') => Some(Radix::Octal),
+                Some('b') => Some(Radix::Binary),
+                _ => None,
+            };
+
+            if let Some(radix) = radix {
+                let marker = self.text.next().unwrap();
+                return self.make_radix_int(radix, &format!("0{marker}"), start);
+            }
+        }
+
+        self.make_decimal_number(ch, start)
+    }
+
+    fn make_radix_int(
+        &mut self,
+        radix: Radix,
+        prefix: &str,
+        start: Pos,
+    ) -> Result<Spanned<NumberLiteral>, LexerError> {
+        let mut digits = String::new();
+
+        while let Some(&ch) = self.text.peek() {
+            if LEGAL_EXIT_CHARS.contains(&ch) || ch.is_ascii_whitespace() {
+                break;
+            } else if RESERVED_CHARS.contains(&ch) {
+                return Err(self.invalid_radix_number(prefix, digits));
+            } else if ch == '_' {
+                let prev_is_digit =
+                    matches!(digits.chars().last(), Some(c) if c.is_digit(radix.radix()));
+
+                self.text.next();
+                digits.push('_');
+
+                let next_is_digit =
+                    matches!(self.text.peek(), Some(&c) if c.is_digit(radix.radix()));
+
+                if !prev_is_digit || !next_is_digit {
+                    return Err(self.invalid_radix_number(prefix, digits));
+                }
+            } else if ch.is_digit(radix.radix()) {
+                digits.push(self.text.next().unwrap());
+            } else {
+                return Err(self.invalid_radix_number(prefix, digits));
+            }
+        }
+
+        if digits.is_empty() {
+            return Err(self.invalid_radix_number(prefix, digits));
+        }
+
+        let span = Span {
+            start,
+            end: self.text.pos,
+        };
+        let cleaned: String = digits.chars().filter(|&c| c != '_').collect();
+
+        match i64::from_str_radix(&cleaned, radix.radix()) {
+            Ok(int) => Ok(Spanned {
+                value: NumberLiteral::Int(int, radix),
+                span,
+            }),
+            Err(_) => Err(self.invalid_radix_number(prefix, digits)),
+        }
+    }
+
+    fn make_decimal_number(
+        &mut self,
+        ch: char,
+        start: Pos,
+    ) -> Result<Spanned<NumberLiteral>, LexerError> {
+        let mut phase = if ch == '.' {
+            NumberPhase::Fraction
+        } else {
+            NumberPhase::Integer
+        };
         let mut num_str = String::from(ch);
+        let mut exponent_has_digit = false;
 
-        while let Some(ch) = self.text.peek() {
-            if LEGAL_EXIT_CHARS.contains(ch) {
+        while let Some(&ch) = self.text.peek() {
+            if LEGAL_EXIT_CHARS.contains(&ch) || ch.is_ascii_whitespace() {
                 break;
-            } else if RESERVED_CHARS.contains(ch) {
-                return Err(LexerError::InvalidNumber)
+            } else if RESERVED_CHARS.contains(&ch) {
+                return Err(self.invalid_number(num_str));
             }
 
-            match self.text.next() {
-                None => break,
-                Some(ch) if ch.is_ascii_whitespace() => break,
+            match ch {
+                '.' => {
+                    if phase != NumberPhase::Integer {
+                        return Err(self.invalid_number(num_str));
+                    }
+
+                    phase = NumberPhase::Fraction;
+                    num_str.push(self.text.next().unwrap());
+                }
+
+                'e' | 'E' => {
+                    if phase == NumberPhase::Exponent {
+                        return Err(self.invalid_number(num_str));
+                    }
+
+                    phase = NumberPhase::Exponent;
+                    num_str.push(self.text.next().unwrap());
+
+                    if let Some('+') | Some('-') = self.text.peek() {
+                        num_str.push(self.text.next().unwrap());
+                    }
+                }
+
+                '_' => {
+                    let prev_is_digit =
+                        matches!(num_str.chars().last(), Some(c) if c.is_ascii_digit());
 
-                Some('.') => {
-                    if has_dot {
-                        return Err(LexerError::InvalidNumber);
+                    self.text.next();
+                    num_str.push('_');
+
+                    let next_is_digit =
+                        matches!(self.text.peek(), Some(c) if c.is_ascii_digit());
+
+                    if !prev_is_digit || !next_is_digit {
+                        return Err(self.invalid_number(num_str));
+                    }
+                }
+
+                ch if ch.is_ascii_digit() => {
+                    if phase == NumberPhase::Exponent {
+                        exponent_has_digit = true;
                     }
 
-                    has_dot = true;
-                    num_str.push('.');
+                    num_str.push(self.text.next().unwrap());
                 }
 
-                Some(ch) => num_str.push(ch),
+                _ => return Err(self.invalid_number(num_str)),
             }
         }
 
-        if has_dot {
-            Ok(Either::Right(num_str.parse::<f64>()?))
+        if phase == NumberPhase::Exponent && !exponent_has_digit {
+            return Err(self.error(LexerErrorKind::InvalidNumber, Some(num_str)));
+        }
+
+        let span = Span {
+            start,
+            end: self.text.pos,
+        };
+        let cleaned: String = num_str.chars().filter(|&c| c != '_').collect();
+
+        if phase == NumberPhase::Integer {
+            match cleaned.parse::<i64>() {
+                Ok(int) => Ok(Spanned {
+                    value: NumberLiteral::Int(int, Radix::Decimal),
+                    span,
+                }),
+                Err(_) => Err(self.error(LexerErrorKind::InvalidNumber, Some(num_str))),
+            }
         } else {
-            Ok(Either::Left(num_str.parse::<i64>()?))
+            match cleaned.parse::<f64>() {
+                Ok(float) => Ok(Spanned {
+                    value: NumberLiteral::Float(float),
+                    span,
+                }),
+                Err(_) => Err(self.error(LexerErrorKind::InvalidNumber, Some(num_str))),
+            }
         }
     }
 
-    fn make_string(&mut self) -> Result<String, LexerError> {
+    fn make_string(&mut self, start: Pos) -> Result<Spanned<String>, LexerError> {
         let mut str = String::new();
         let mut terminated = false;
 
-        while let Some(ch) = self.text.next() {
-            if ch == '"' {
-                terminated = true;
-                break;
-            } else {
-                str.push(ch);
+        while self.text.peek().is_some() {
+            let char_start = self.text.pos;
+            let ch = self.text.next().unwrap();
+
+            match ch {
+                '"' => {
+                    terminated = true;
+                    break;
+                }
+                '\\' => {
+                    str.push(self.make_escape(char_start)?);
+                }
+                ch => str.push(ch),
             }
         }
 
         if terminated {
-            Ok(str)
+            Ok(Spanned {
+                value: str,
+                span: Span {
+                    start,
+                    end: self.text.pos,
+                },
+            })
         } else {
-            Err(LexerError::UnterminatedString)
+            Err(self.error(LexerErrorKind::UnterminatedString, Some(str)))
+        }
+    }
+
+    /// Decode the escape sequence following a `\` already consumed at
+    /// `backslash_pos`.
+    fn make_escape(&mut self, backslash_pos: Pos) -> Result<char, LexerError> {
+        let Some(ch) = self.text.next() else {
+            return Err(self.error_at(
+                backslash_pos,
+                LexerErrorKind::InvalidEscape,
+                Some("\\".to_string()),
+            ));
+        };
+
+        match ch {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            '0' => Ok('\0'),
+            '\\' => Ok('\\'),
+            '"' => Ok('"'),
+            'u' => self.make_unicode_escape(backslash_pos),
+            other => Err(self.error_at(
+                backslash_pos,
+                LexerErrorKind::InvalidEscape,
+                Some(format!("\\{other}")),
+            )),
         }
     }
 
-    fn make_symbol(&mut self, entry_char: Option<char>) -> Result<String, LexerError> {
+    /// Decode a `\u{XXXX}` escape (1-6 hex digits) following the `\u`
+    /// already consumed at `backslash_pos`.
+    fn make_unicode_escape(&mut self, backslash_pos: Pos) -> Result<char, LexerError> {
+        if self.text.peek() != Some(&'{') {
+            return Err(self.error_at(
+                backslash_pos,
+                LexerErrorKind::InvalidEscape,
+                Some("\\u".to_string()),
+            ));
+        }
+        self.text.next();
+
+        let mut hex = String::new();
+
+        while let Some(&ch) = self.text.peek() {
+            if ch == '}' {
+                break;
+            } else if !ch.is_ascii_hexdigit() || hex.len() >= 6 {
+                return Err(self.error_at(
+                    backslash_pos,
+                    LexerErrorKind::InvalidEscape,
+                    Some(format!("\\u{{{hex}")),
+                ));
+            }
+
+            hex.push(self.text.next().unwrap());
+        }
+
+        if self.text.next() != Some('}') {
+            return Err(self.error_at(
+                backslash_pos,
+                LexerErrorKind::InvalidEscape,
+                Some(format!("\\u{{{hex}")),
+            ));
+        }
+
+        if hex.is_empty() {
+            return Err(self.error_at(
+                backslash_pos,
+                LexerErrorKind::InvalidEscape,
+                Some("\\u{}".to_string()),
+            ));
+        }
+
+        let code = u32::from_str_radix(&hex, 16).map_err(|_| {
+            self.error_at(
+                backslash_pos,
+                LexerErrorKind::InvalidEscape,
+                Some(format!("\\u{{{hex}}}")),
+            )
+        })?;
+
+        char::from_u32(code).ok_or_else(|| {
+            self.error_at(
+                backslash_pos,
+                LexerErrorKind::InvalidEscape,
+                Some(format!("\\u{{{hex}}}")),
+            )
+        })
+    }
+
+    fn make_symbol(
+        &mut self,
+        entry_char: Option<char>,
+        start: Pos,
+    ) -> Result<Spanned<String>, LexerError> {
         let mut symbol_name = match entry_char {
             Some(ch) => String::from(ch),
             None => String::new(),
@@ -136,7 +577,7 @@ impl<'s> Lexer<'s> {
             if LEGAL_EXIT_CHARS.contains(ch) || ch.is_ascii_whitespace() {
                 break;
             } else if RESERVED_CHARS.contains(ch) {
-                return Err(LexerError::InvalidSymbolName);
+                return Err(self.error(LexerErrorKind::InvalidSymbolName, Some(symbol_name)));
             }
 
             // SAFETY: We just peeked. This is safe.
@@ -144,51 +585,377 @@ impl<'s> Lexer<'s> {
             symbol_name.push(ch);
         }
 
-        Ok(symbol_name)
+        Ok(Spanned {
+            value: symbol_name,
+            span: Span {
+                start,
+                end: self.text.pos,
+            },
+        })
     }
 
-    pub fn tokenize(&mut self) -> Result<Vec<Token>, LexerError> {
-        let mut tokens = Vec::new();
+    /// Advance past the current bad lexeme up to the next point it is
+    /// safe to resume lexing from (whitespace, a closing brace, or
+    /// EOF), returning whatever was skipped.
+    fn resync(&mut self) -> String {
+        let mut skipped = String::new();
 
-        while let Some(ch) = self.text.next() {
-            match ch {
-                // Skip all whitespace
-                ch if ch.is_ascii_whitespace() => (),
-
-                // Numbers
-                ch if ch.is_digit(10) || ch == '.' => match self.make_number(ch) {
-                    Ok(Either::Left(int)) => tokens.push(Token::Int(int)),
-                    Ok(Either::Right(float)) => tokens.push(Token::Float(float)),
-                    Err(err) => return Err(err),
+        while let Some(ch) = self.text.peek() {
+            if ch.is_ascii_whitespace() || LEGAL_EXIT_CHARS.contains(ch) {
+                break;
+            }
+
+            skipped.push(self.text.next().unwrap());
+        }
+
+        skipped
+    }
+
+    /// Consume a `# ...` or `// ...` line comment, given its marker,
+    /// up to (but not including) the newline that ends it.
+    fn lex_line_comment(
+        &mut self,
+        marker: &str,
+        start: Pos,
+    ) -> Result<Option<Spanned<Token>>, LexerError> {
+        let mut comment = String::from(marker);
+
+        while let Some(&ch) = self.text.peek() {
+            if ch == '\n' {
+                break;
+            }
+
+            comment.push(self.text.next().unwrap());
+        }
+
+        if self.keep_comments {
+            Ok(Some(Spanned {
+                value: Token::Comment(comment),
+                span: Span {
+                    start,
+                    end: self.text.pos,
                 },
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Consume a `{- ... -}` block comment, tracking nesting depth so
+    /// that `{- a {- b -} c -}` closes at the outermost `-}`.
+    fn lex_block_comment(&mut self, start: Pos) -> Result<Option<Spanned<Token>>, LexerError> {
+        let mut comment = String::from("{-");
+        let mut depth = 1usize;
+
+        loop {
+            match self.text.next() {
+                None => return Err(self.error(LexerErrorKind::UnterminatedComment, Some(comment))),
+
+                Some('{') if self.text.peek() == Some(&'-') => {
+                    self.text.next();
+                    comment.push_str("{-");
+                    depth += 1;
+                }
+
+                Some('-') if self.text.peek() == Some(&'}') => {
+                    self.text.next();
+                    comment.push_str("-}");
+                    depth -= 1;
 
-                // Symbols
-                '\x27' => match self.make_symbol(None) {
-                    Ok(symbol) => tokens.push(Token::Symbol(symbol)),
-                    Err(err) => return Err(err),
+                    if depth == 0 {
+                        break;
+                    }
+                }
+
+                Some(ch) => comment.push(ch),
+            }
+        }
+
+        if self.keep_comments {
+            Ok(Some(Spanned {
+                value: Token::Comment(comment),
+                span: Span {
+                    start,
+                    end: self.text.pos,
                 },
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn lex_one(&mut self, ch: char, start: Pos) -> Result<Option<Spanned<Token>>, LexerError> {
+        match ch {
+            // Skip all whitespace
+            ch if ch.is_ascii_whitespace() => Ok(None),
+
+            // Comments
+            '#' => self.lex_line_comment("#", start),
+            '/' if self.text.peek() == Some(&'/') => {
+                self.text.next();
+                self.lex_line_comment("//", start)
+            }
+            '{' if self.text.peek() == Some(&'-') => {
+                self.text.next();
+                self.lex_block_comment(start)
+            }
+
+            // Numbers
+            ch if ch.is_digit(10) || ch == '.' => match self.make_number(ch, start)? {
+                Spanned {
+                    value: NumberLiteral::Int(int, radix),
+                    span,
+                } => Ok(Some(Spanned {
+                    value: Token::Int(int, radix),
+                    span,
+                })),
+                Spanned {
+                    value: NumberLiteral::Float(float),
+                    span,
+                } => Ok(Some(Spanned {
+                    value: Token::Float(float),
+                    span,
+                })),
+            },
+
+            // Symbols
+            '\x27' => {
+                let Spanned { value: symbol, span } = self.make_symbol(None, start)?;
+                Ok(Some(Spanned {
+                    value: Token::Symbol(symbol),
+                    span,
+                }))
+            }
 
-                // Strings
-                '"' => match self.make_string() {
-                    Ok(string) => tokens.push(Token::String(string)),
-                    Err(err) => return Err(err),
+            // Strings
+            '"' => {
+                let Spanned { value: string, span } = self.make_string(start)?;
+                Ok(Some(Spanned {
+                    value: Token::String(string),
+                    span,
+                }))
+            }
+
+            // Quotations
+            '{' => Ok(Some(Spanned {
+                value: Token::OpeningBrace,
+                span: Span {
+                    start,
+                    end: self.text.pos,
                 },
+            })),
+            '}' => Ok(Some(Spanned {
+                value: Token::ClosingBrace,
+                span: Span {
+                    start,
+                    end: self.text.pos,
+                },
+            })),
+
+            // Function calls
+            ch if ch.is_ascii() => {
+                let Spanned { value: symbol, span } = self.make_symbol(Some(ch), start)?;
+                Ok(Some(Spanned {
+                    value: Token::Function(symbol),
+                    span,
+                }))
+            }
 
-                // Quotations
-                '{' => tokens.push(Token::OpeningBrace),
-                '}' => tokens.push(Token::ClosingBrace),
+            _ => Err(self.error(LexerErrorKind::InvalidToken, Some(ch.to_string()))),
+        }
+    }
+
+    /// Tokenize the whole input, recovering from lexing errors instead
+    /// of aborting on the first one. Every byte of input other than
+    /// whitespace and stripped comments lies within the span of either
+    /// a real token or a `Token::Error`, and every `LexerError`
+    /// encountered along the way is collected so callers can report
+    /// them all at once.
+    pub fn tokenize_recovering(&mut self) -> (Vec<Spanned<Token>>, Vec<LexerError>) {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
 
-                // Function calls
-                ch if ch.is_ascii() => match self.make_symbol(Some(ch)) {
-                    Ok(symbol) => tokens.push(Token::Function(symbol)),
-                    Err(err) => return Err(err),
+        while self.text.peek().is_some() {
+            let start = self.text.pos;
+            let ch = self.text.next().unwrap();
+
+            match self.lex_one(ch, start) {
+                Ok(Some(token)) => tokens.push(token),
+                Ok(None) => (),
+                Err(err) => {
+                    let mut lexeme = err.offending_token.clone().unwrap_or_default();
+                    lexeme.push_str(&self.resync());
+
+                    tokens.push(Spanned {
+                        value: Token::Error(lexeme),
+                        span: Span {
+                            start,
+                            end: self.text.pos,
+                        },
+                    });
+                    errors.push(err);
                 }
+            }
+        }
+
+        tokens.push(Spanned {
+            value: Token::EOF,
+            span: Span {
+                start: self.text.pos,
+                end: self.text.pos,
+            },
+        });
+
+        (tokens, errors)
+    }
 
-                _ => return Err(LexerError::InvalidToken),
+    /// Produce the next single token, skipping over whitespace, without
+    /// materializing the rest of the input. Returns `Token::EOF` once
+    /// the input is exhausted, so callers can keep calling this with
+    /// one token of lookahead instead of allocating a full `Vec`.
+    pub fn next_token(&mut self) -> Result<Spanned<Token>, LexerError> {
+        loop {
+            let start = self.text.pos;
+
+            let Some(ch) = self.text.next() else {
+                return Ok(Spanned {
+                    value: Token::EOF,
+                    span: Span { start, end: start },
+                });
+            };
+
+            if let Some(token) = self.lex_one(ch, start)? {
+                return Ok(token);
             }
         }
+    }
+
+    pub fn tokenize(&mut self) -> Result<Vec<Spanned<Token>>, LexerError> {
+        self.by_ref().collect()
+    }
+}
+
+impl<'s> Iterator for Lexer<'s> {
+    type Item = Result<Spanned<Token>, LexerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.next_token() {
+            Ok(token) => {
+                if token.value == Token::EOF {
+                    self.done = true;
+                }
+                Some(Ok(token))
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lex(src: &str) -> Result<Spanned<Token>, LexerError> {
+        Lexer::new(src).next_token()
+    }
+
+    #[test]
+    fn rejects_trailing_underscore() {
+        assert!(lex("1_").is_err());
+    }
+
+    #[test]
+    fn rejects_trailing_underscore_after_separated_digits() {
+        assert!(lex("1_000_").is_err());
+    }
+
+    #[test]
+    fn rejects_doubled_underscore() {
+        assert!(lex("1__2").is_err());
+    }
+
+    #[test]
+    fn rejects_underscore_before_dot() {
+        assert!(lex("10_.5").is_err());
+    }
+
+    #[test]
+    fn recovering_error_token_carries_the_full_bad_radix_lexeme() {
+        let (tokens, errors) = Lexer::new("0xGG").tokenize_recovering();
+
+        assert_eq!(tokens[0].value, Token::Error("0xGG".to_string()));
+        assert_eq!(tokens[0].span.start.col, 1);
+        assert_eq!(tokens[0].span.end.col, 5);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn accepts_radix_prefixes() {
+        assert_eq!(lex("0xFF").unwrap().value, Token::Int(255, Radix::Hexadecimal));
+        assert_eq!(lex("0o17").unwrap().value, Token::Int(15, Radix::Octal));
+        assert_eq!(lex("0b10").unwrap().value, Token::Int(2, Radix::Binary));
+    }
+
+    #[test]
+    fn rejects_invalid_hex_digits() {
+        assert!(lex("0xGG").is_err());
+    }
+
+    #[test]
+    fn accepts_exponents_with_a_sign() {
+        assert_eq!(lex("1.5e-10").unwrap().value, Token::Float(1.5e-10));
+    }
+
+    #[test]
+    fn rejects_exponent_with_no_digits() {
+        assert!(lex("1e").is_err());
+    }
+
+    #[test]
+    fn decodes_unicode_escape() {
+        assert_eq!(lex(r#""\u{48}""#).unwrap().value, Token::String("H".to_string()));
+    }
+
+    #[test]
+    fn rejects_unicode_escape_for_a_lone_surrogate() {
+        assert!(lex(r#""\u{D800}""#).is_err());
+    }
+
+    #[test]
+    fn lexes_nested_block_comments() {
+        let token = Lexer::new("{- a {- b -} c -}")
+            .keep_comments(true)
+            .next_token()
+            .unwrap();
+
+        assert_eq!(token.value, Token::Comment("{- a {- b -} c -}".to_string()));
+    }
+
+    #[test]
+    fn rejects_unclosed_block_comment() {
+        assert!(Lexer::new("{- unclosed").next_token().is_err());
+    }
 
-        tokens.push(Token::EOF);
-        Ok(tokens)
+    #[test]
+    fn recovering_collects_every_error_in_a_multi_error_run() {
+        let (tokens, errors) = Lexer::new("0xGG 1_ 5").tokenize_recovering();
+
+        assert_eq!(
+            tokens.iter().map(|t| &t.value).collect::<Vec<_>>(),
+            vec![
+                &Token::Error("0xGG".to_string()),
+                &Token::Error("1_".to_string()),
+                &Token::Int(5, Radix::Decimal),
+                &Token::EOF,
+            ]
+        );
+        assert_eq!(errors.len(), 2);
     }
 }
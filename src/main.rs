@@ -2,16 +2,17 @@ use std::fs;
 
 mod lexer;
 
-fn run(text: &str) -> Result<Vec<lexer::Token>, lexer::LexerError> {
-    let mut lexer = lexer::Lexer::new(text);
+fn run(text: &str, path: &str) -> Result<Vec<lexer::Spanned<lexer::Token>>, lexer::LexerError> {
+    let mut lexer = lexer::Lexer::new(text).with_file_name(path);
 
     lexer.tokenize()
 }
 
 fn main() -> std::io::Result<()> {
-    let file = fs::read_to_string("examples/01.lin")?;
+    let path = "examples/01.lin";
+    let file = fs::read_to_string(path)?;
 
-    match run(&file) {
+    match run(&file, path) {
         Ok(tokens) => {
             println!("{:?}", tokens);
             Ok(())